@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+
+/// Auth endpoint the WebSocket handshake validates bearer tokens against. Overridable
+/// via the `AUTH_ENDPOINT` env var for local/staging deployments.
+const DEFAULT_AUTH_ENDPOINT: &str = "https://auth.internal.example.com/v1/verify";
+
+/// Identity and metadata the auth endpoint returns for a verified bearer token. Bound
+/// into the connection once at handshake time so clients can't spoof `user_id` later.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub user_id: String,
+    pub display_name: String,
+    #[serde(default)]
+    pub properties: serde_json::Value,
+}
+
+#[derive(Debug)]
+pub enum AuthError {
+    MissingToken,
+    Request(reqwest::Error),
+    Rejected(reqwest::StatusCode),
+}
+
+impl std::fmt::Display for AuthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuthError::MissingToken => write!(f, "missing bearer token"),
+            AuthError::Request(err) => write!(f, "auth endpoint request failed: {}", err),
+            AuthError::Rejected(status) => {
+                write!(f, "auth endpoint rejected token with status {}", status)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AuthError {}
+
+impl From<reqwest::Error> for AuthError {
+    fn from(err: reqwest::Error) -> Self {
+        AuthError::Request(err)
+    }
+}
+
+/// Validates `token` against the configured auth endpoint and returns the verified
+/// profile on success. Called once at WebSocket handshake time, before `ws.on_upgrade`.
+pub async fn verify(token: &str) -> Result<Profile, AuthError> {
+    if token.is_empty() {
+        return Err(AuthError::MissingToken);
+    }
+
+    let endpoint =
+        std::env::var("AUTH_ENDPOINT").unwrap_or_else(|_| DEFAULT_AUTH_ENDPOINT.to_string());
+    let client = reqwest::Client::new();
+    let res = client.post(endpoint).bearer_auth(token).send().await?;
+
+    if !res.status().is_success() {
+        return Err(AuthError::Rejected(res.status()));
+    }
+
+    Ok(res.json::<Profile>().await?)
+}
@@ -1,19 +1,29 @@
+mod auth;
+mod offsets;
+
 use axum::{
-    extract::{ws::{WebSocketUpgrade, WebSocket, Message}, ConnectInfo, State},
+    extract::{ws::{WebSocketUpgrade, WebSocket, Message}, ConnectInfo, Query, State},
+    http::StatusCode,
     response::IntoResponse,
     routing::{get, post},
     Json, Router,
 };
 use fluvio::{Fluvio, Offset};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, net::SocketAddr, sync::Arc};
-use tokio::{sync::{broadcast, Mutex}, task};
+use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Duration};
+use tokio::{sync::{broadcast, Mutex}, task, time::timeout};
 use tower_http::cors::CorsLayer;
 use uuid::Uuid;
-use futures_util::{StreamExt, SinkExt};
+use futures_util::{future::join_all, StreamExt, SinkExt};
 
 const TOPIC_NAME: &str = "demo-topic-1";
 const WEBHOOK_URL: &str = "https://infinyon.cloud/webhooks/v1/LHac7AZWw8oQ6xGTd7hyUjx8RhM7B3SA3doPSxG4vQxr1zbeZzYuiWoKJOZMxQDf";
+/// Number of partitions backing `TOPIC_NAME`. A production deployment would read this
+/// from topic metadata; fixed here to match how the demo topic was provisioned.
+const PARTITION_COUNT: u32 = 4;
+/// How long `fetch_history` waits for the next record before concluding the partition
+/// has no more history to offer right now, rather than tailing forever for new writes.
+const FETCH_HISTORY_IDLE_TIMEOUT: Duration = Duration::from_secs(2);
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct EditEvent {
@@ -25,6 +35,11 @@ struct EditEvent {
     timestamp: u64,
 }
 
+/// Correlation id a client attaches to a request-style frame so it can match the
+/// eventual reply. Notification-style frames (Edit/Typing/Cursor/Join/Leave) never
+/// carry one and get no reply.
+type RequestId = u64;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 enum ClientMessage {
@@ -33,23 +48,171 @@ enum ClientMessage {
     Cursor { doc_id: String, user_id: String, position: usize },
     Join { doc_id: String, user_id: String },
     Leave { doc_id: String, user_id: String },
+    /// Replays previously-forwarded edits for `doc_id`, answered with `RpcResponse::History`.
+    /// `from_offsets` is a partition -> offset map, since partitions have independent
+    /// offset spaces; a partition missing from the map is replayed from the beginning.
+    /// To page through history, re-issue this with the `partition`/`offset` pairs from
+    /// the last record returned for each partition, each incremented by one; a partition
+    /// that returned no records on this page keeps the offset it was sent with.
+    FetchHistory {
+        request_id: RequestId,
+        doc_id: String,
+        #[serde(default)]
+        from_offsets: HashMap<u32, i64>,
+        limit: usize,
+    },
+    /// Answered with `RpcResponse::Members`, the current user list from `rooms`.
+    ListMembers { request_id: RequestId, doc_id: String },
+    /// Narrows the events this socket receives for `doc_id`. Replaces any filter set by
+    /// an earlier `Subscribe` (or implied default) for the room.
+    Subscribe {
+        doc_id: String,
+        #[serde(default)]
+        kinds: Option<Vec<String>>,
+        #[serde(default)]
+        ops: Option<Vec<String>>,
+        #[serde(default)]
+        user_ids: Option<Vec<String>>,
+    },
+}
+
+/// What a socket wants streamed to it for the room it has joined. `None` on any field
+/// means "no restriction on that dimension".
+#[derive(Debug, Clone, Default)]
+struct SubscriptionFilter {
+    kinds: Option<Vec<String>>,
+    ops: Option<Vec<String>>,
+    user_ids: Option<Vec<String>>,
+}
+
+impl SubscriptionFilter {
+    fn allows_kind(&self, kind: &str) -> bool {
+        self.kinds.as_ref().map_or(true, |kinds| kinds.iter().any(|k| k == kind))
+    }
+
+    fn allows_op(&self, operation: &str) -> bool {
+        self.ops.as_ref().map_or(true, |ops| ops.iter().any(|o| o == operation))
+    }
+
+    fn allows_user(&self, user_id: &str) -> bool {
+        self.user_ids.as_ref().map_or(true, |user_ids| user_ids.iter().any(|u| u == user_id))
+    }
+}
+
+/// A historical edit tagged with the partition/offset it was read from, so a client can
+/// compute the `from_offsets` for its next `FetchHistory` page.
+#[derive(Debug, Clone, Serialize)]
+struct HistoryRecord {
+    partition: u32,
+    offset: i64,
+    event: EditEvent,
+}
+
+/// Reply to a `ClientMessage` request variant, tagged with the same `request_id` so the
+/// client can match it against the call that produced it.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type")]
+enum RpcResponse {
+    #[serde(rename = "history")]
+    History { request_id: RequestId, doc_id: String, events: Vec<HistoryRecord> },
+    #[serde(rename = "members")]
+    Members { request_id: RequestId, doc_id: String, user_ids: Vec<String> },
+    #[serde(rename = "error")]
+    Error { request_id: RequestId, error: String },
+}
+
+/// A connected user's last known cursor position and typing state, as broadcast in a
+/// room's presence roster.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct UserPresence {
+    user_id: String,
+    cursor: Option<usize>,
+    is_typing: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+enum PresenceEvent {
+    #[serde(rename = "typing")]
+    Typing { doc_id: String, user_id: String, is_typing: bool },
+    #[serde(rename = "cursor")]
+    Cursor { doc_id: String, user_id: String, position: usize },
+    #[serde(rename = "presence")]
+    Roster { doc_id: String, users: Vec<UserPresence> },
 }
 
 #[derive(Clone)]
 struct AppState {
     fluvio: Fluvio,
-    tx: broadcast::Sender<EditEvent>,
+    room_channels: Arc<Mutex<HashMap<String, broadcast::Sender<EditEvent>>>>, // doc_id -> broadcast sender
+    presence_channels: Arc<Mutex<HashMap<String, broadcast::Sender<PresenceEvent>>>>, // doc_id -> broadcast sender
     rooms: Arc<Mutex<HashMap<String, Vec<String>>>>, // doc_id -> user_ids
+    presence: Arc<Mutex<HashMap<String, HashMap<String, UserPresence>>>>, // doc_id -> user_id -> presence
+    offset_store: Arc<offsets::OffsetStore>,
+}
+
+impl AppState {
+    /// Looks up the broadcast channel for `doc_id`, creating it if this is the first
+    /// subscriber or publisher to touch that room.
+    async fn room_sender(&self, doc_id: &str) -> broadcast::Sender<EditEvent> {
+        let mut channels = self.room_channels.lock().await;
+        channels
+            .entry(doc_id.to_string())
+            .or_insert_with(|| broadcast::channel(100).0)
+            .clone()
+    }
+
+    /// Looks up the presence broadcast channel for `doc_id`, creating it if needed.
+    async fn presence_sender(&self, doc_id: &str) -> broadcast::Sender<PresenceEvent> {
+        let mut channels = self.presence_channels.lock().await;
+        channels
+            .entry(doc_id.to_string())
+            .or_insert_with(|| broadcast::channel(100).0)
+            .clone()
+    }
+
+    /// Publishes the current roster for `doc_id` so every socket in the room (including
+    /// one that just joined) learns who else is editing and where their cursors are.
+    async fn broadcast_roster(&self, doc_id: &str) {
+        let users: Vec<UserPresence> = {
+            let presence = self.presence.lock().await;
+            presence
+                .get(doc_id)
+                .map(|users| users.values().cloned().collect())
+                .unwrap_or_default()
+        };
+        let sender = self.presence_sender(doc_id).await;
+        let _ = sender.send(PresenceEvent::Roster { doc_id: doc_id.to_string(), users });
+    }
+
+    /// Removes every map entry for `doc_id` once its user list is empty, so a room with
+    /// no connected users doesn't leak a broadcast sender and empty presence map forever.
+    async fn prune_room_if_empty(&self, doc_id: &str) {
+        let is_empty = {
+            let rooms = self.rooms.lock().await;
+            rooms.get(doc_id).map_or(true, |users| users.is_empty())
+        };
+        if !is_empty {
+            return;
+        }
+        self.rooms.lock().await.remove(doc_id);
+        self.presence.lock().await.remove(doc_id);
+        self.room_channels.lock().await.remove(doc_id);
+        self.presence_channels.lock().await.remove(doc_id);
+    }
 }
 
 #[tokio::main]
 async fn main() {
     let fluvio = Fluvio::connect().await.expect("Failed to connect to Fluvio");
-    let (tx, _) = broadcast::channel(100);
+    let offset_store = offsets::OffsetStore::load(offsets::DEFAULT_OFFSET_STORE_PATH).await;
     let state = Arc::new(AppState {
         fluvio,
-        tx,
+        room_channels: Arc::new(Mutex::new(HashMap::new())),
+        presence_channels: Arc::new(Mutex::new(HashMap::new())),
         rooms: Arc::new(Mutex::new(HashMap::new())),
+        presence: Arc::new(Mutex::new(HashMap::new())),
+        offset_store: Arc::new(offset_store),
     });
 
     let consumer_state = state.clone();
@@ -87,60 +250,199 @@ async fn handle_send(
     "Message sent"
 }
 
+#[derive(Debug, Deserialize)]
+struct WsAuthQuery {
+    token: Option<String>,
+}
+
 async fn ws_handler(
     ws: WebSocketUpgrade,
     State(state): State<Arc<AppState>>,
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
-) -> impl IntoResponse {
-    println!("New WebSocket connection from {}", addr);
-    ws.on_upgrade(move |socket| handle_ws(socket, state))
+    Query(query): Query<WsAuthQuery>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let token = query.token.unwrap_or_default();
+    let profile = auth::verify(&token).await.map_err(|err| {
+        println!("WebSocket auth rejected for {}: {}", addr, err);
+        StatusCode::UNAUTHORIZED
+    })?;
+
+    println!(
+        "New WebSocket connection from {} authenticated as {}",
+        addr, profile.user_id
+    );
+    Ok(ws.on_upgrade(move |socket| handle_ws(socket, state, profile)))
+}
+
+/// Awaits the next event on `rx` if the socket has joined a room, or never resolves
+/// otherwise. Lets `handle_ws` select! on a per-room subscription that may not exist yet.
+async fn recv_room_event(
+    rx: &mut Option<broadcast::Receiver<EditEvent>>,
+) -> Result<EditEvent, broadcast::error::RecvError> {
+    match rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
 }
 
-async fn handle_ws(mut socket: WebSocket, state: Arc<AppState>) {
-    let mut rx = state.tx.subscribe();
+/// Same as `recv_room_event`, for the parallel presence broadcast channel.
+async fn recv_presence_event(
+    rx: &mut Option<broadcast::Receiver<PresenceEvent>>,
+) -> Result<PresenceEvent, broadcast::error::RecvError> {
+    match rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+async fn handle_ws(mut socket: WebSocket, state: Arc<AppState>, profile: auth::Profile) {
     let id = Uuid::new_v4().to_string();
     println!("WebSocket {} connected", id);
 
     let mut current_doc = String::new();
-    let mut user_id = String::new();
+    let user_id = profile.user_id;
+    let mut rx: Option<broadcast::Receiver<EditEvent>> = None;
+    let mut presence_rx: Option<broadcast::Receiver<PresenceEvent>> = None;
+    let mut filter = SubscriptionFilter::default();
+    // FetchHistory replies are computed on a background task (see the FetchHistory arm
+    // below) and delivered back here, so a slow/empty history replay can't stall this
+    // socket's main select loop.
+    let (rpc_tx, mut rpc_rx) = tokio::sync::mpsc::unbounded_channel::<RpcResponse>();
 
     loop {
         tokio::select! {
             Some(Ok(Message::Text(msg))) = socket.next() => {
                 if let Ok(message) = serde_json::from_str::<ClientMessage>(&msg) {
                     match message {
-                        ClientMessage::Edit(edit) => {
+                        ClientMessage::Edit(mut edit) => {
+                            // Stamp the handshake-verified identity; never trust the
+                            // `user_id` a client put in its own payload.
+                            edit.user_id = user_id.clone();
                             let producer = state.fluvio.topic_producer(TOPIC_NAME).await.unwrap();
                             let json = serde_json::to_string(&edit).unwrap();
                             let _ = producer.send(&edit.doc_id, json).await;
                         },
-                        ClientMessage::Typing { doc_id, user_id, is_typing } => {
-                            let indicator = format!("{{\"type\": \"typing\", \"user_id\": \"{}\", \"is_typing\": {}}}", user_id, is_typing);
-                            let _ = socket.send(Message::Text(indicator)).await;
+                        ClientMessage::Typing { doc_id, user_id: _, is_typing } => {
+                            let mut presence = state.presence.lock().await;
+                            if let Some(user) = presence.get_mut(&doc_id).and_then(|users| users.get_mut(&user_id)) {
+                                user.is_typing = is_typing;
+                            }
+                            drop(presence);
+                            let sender = state.presence_sender(&doc_id).await;
+                            let _ = sender.send(PresenceEvent::Typing { doc_id, user_id: user_id.clone(), is_typing });
                         },
-                        ClientMessage::Cursor { doc_id, user_id, position } => {
-                            let cursor = format!("{{\"type\": \"cursor\", \"user_id\": \"{}\", \"position\": {}}}", user_id, position);
-                            let _ = socket.send(Message::Text(cursor)).await;
+                        ClientMessage::Cursor { doc_id, user_id: _, position } => {
+                            let mut presence = state.presence.lock().await;
+                            if let Some(user) = presence.get_mut(&doc_id).and_then(|users| users.get_mut(&user_id)) {
+                                user.cursor = Some(position);
+                            }
+                            drop(presence);
+                            let sender = state.presence_sender(&doc_id).await;
+                            let _ = sender.send(PresenceEvent::Cursor { doc_id, user_id: user_id.clone(), position });
                         },
-                        ClientMessage::Join { doc_id, user_id: uid } => {
+                        ClientMessage::Join { doc_id, user_id: _ } => {
                             current_doc = doc_id.clone();
-                            user_id = uid.clone();
+                            filter = SubscriptionFilter::default();
+                            rx = Some(state.room_sender(&doc_id).await.subscribe());
+                            presence_rx = Some(state.presence_sender(&doc_id).await.subscribe());
+
                             let mut rooms = state.rooms.lock().await;
-                            rooms.entry(doc_id.clone()).or_default().push(uid.clone());
-                            println!("User {} joined {}", uid, doc_id);
+                            rooms.entry(doc_id.clone()).or_default().push(user_id.clone());
+                            drop(rooms);
+
+                            let mut presence = state.presence.lock().await;
+                            presence.entry(doc_id.clone()).or_default().insert(
+                                user_id.clone(),
+                                UserPresence { user_id: user_id.clone(), cursor: None, is_typing: false },
+                            );
+                            drop(presence);
+
+                            println!("User {} joined {}", user_id, doc_id);
+                            state.broadcast_roster(&doc_id).await;
                         },
-                        ClientMessage::Leave { doc_id, user_id: uid } => {
+                        ClientMessage::Leave { doc_id, user_id: _ } => {
+                            if doc_id == current_doc {
+                                rx = None;
+                                presence_rx = None;
+                            }
                             let mut rooms = state.rooms.lock().await;
                             if let Some(users) = rooms.get_mut(&doc_id) {
-                                users.retain(|u| u != &uid);
+                                users.retain(|u| u != &user_id);
+                            }
+                            drop(rooms);
+
+                            let mut presence = state.presence.lock().await;
+                            if let Some(users) = presence.get_mut(&doc_id) {
+                                users.remove(&user_id);
+                            }
+                            drop(presence);
+
+                            println!("User {} left {}", user_id, doc_id);
+                            state.broadcast_roster(&doc_id).await;
+                            state.prune_room_if_empty(&doc_id).await;
+                        },
+                        ClientMessage::FetchHistory { request_id, doc_id, from_offsets, limit } => {
+                            // Replaying up to PARTITION_COUNT partitions can take
+                            // seconds; do it off the connection's select loop so other
+                            // inbound messages and room/presence broadcasts keep flowing.
+                            let state = state.clone();
+                            let rpc_tx = rpc_tx.clone();
+                            task::spawn(async move {
+                                let response = match fetch_history(state, doc_id.clone(), from_offsets, limit).await {
+                                    Ok(events) => RpcResponse::History { request_id, doc_id, events },
+                                    Err(err) => RpcResponse::Error { request_id, error: err.to_string() },
+                                };
+                                let _ = rpc_tx.send(response);
+                            });
+                        },
+                        ClientMessage::ListMembers { request_id, doc_id } => {
+                            let rooms = state.rooms.lock().await;
+                            let user_ids = rooms.get(&doc_id).cloned().unwrap_or_default();
+                            drop(rooms);
+                            let response = RpcResponse::Members { request_id, doc_id, user_ids };
+                            let _ = socket.send(Message::Text(serde_json::to_string(&response).unwrap())).await;
+                        },
+                        ClientMessage::Subscribe { doc_id, kinds, ops, user_ids } => {
+                            if doc_id == current_doc {
+                                filter = SubscriptionFilter { kinds, ops, user_ids };
                             }
-                            println!("User {} left {}", uid, doc_id);
                         }
                     }
                 }
             },
-            Ok(event) = rx.recv() => {
-                let json = serde_json::to_string(&event).unwrap();
+            Ok(event) = recv_room_event(&mut rx) => {
+                if filter.allows_kind("edit") && filter.allows_op(&event.operation) && filter.allows_user(&event.user_id) {
+                    let json = serde_json::to_string(&event).unwrap();
+                    if socket.send(Message::Text(json)).await.is_err() {
+                        println!("WS {}: send failed, disconnecting", id);
+                        break;
+                    }
+                }
+            },
+            Ok(event) = recv_presence_event(&mut presence_rx) => {
+                // Typing/cursor updates go to the *other* sockets in the room; the
+                // originating connection already knows its own state and doesn't need
+                // it echoed back. Roster snapshots are for everyone, including the
+                // socket that just joined.
+                let passes_filter = match &event {
+                    PresenceEvent::Typing { user_id: origin, .. } => {
+                        origin != &user_id && filter.allows_kind("typing") && filter.allows_user(origin)
+                    },
+                    PresenceEvent::Cursor { user_id: origin, .. } => {
+                        origin != &user_id && filter.allows_kind("cursor") && filter.allows_user(origin)
+                    },
+                    PresenceEvent::Roster { .. } => true,
+                };
+                if passes_filter {
+                    let json = serde_json::to_string(&event).unwrap();
+                    if socket.send(Message::Text(json)).await.is_err() {
+                        println!("WS {}: send failed, disconnecting", id);
+                        break;
+                    }
+                }
+            },
+            Some(response) = rpc_rx.recv() => {
+                let json = serde_json::to_string(&response).unwrap();
                 if socket.send(Message::Text(json)).await.is_err() {
                     println!("WS {}: send failed, disconnecting", id);
                     break;
@@ -155,28 +457,125 @@ async fn handle_ws(mut socket: WebSocket, state: Arc<AppState>) {
         if let Some(users) = rooms.get_mut(&current_doc) {
             users.retain(|u| u != &user_id);
         }
+        drop(rooms);
+
+        let mut presence = state.presence.lock().await;
+        if let Some(users) = presence.get_mut(&current_doc) {
+            users.remove(&user_id);
+        }
+        drop(presence);
+
         println!("User {} disconnected from {}", user_id, current_doc);
+        state.broadcast_roster(&current_doc).await;
+        state.prune_room_if_empty(&current_doc).await;
     }
 }
 
+/// Spawns one consumer task per partition of `TOPIC_NAME` and waits for all of them.
 async fn consume_and_forward(state: Arc<AppState>) {
+    let mut tasks = Vec::with_capacity(PARTITION_COUNT as usize);
+    for partition in 0..PARTITION_COUNT {
+        let state = state.clone();
+        tasks.push(task::spawn(async move {
+            consume_partition(state, partition).await;
+        }));
+    }
+
+    for task in tasks {
+        let _ = task.await;
+    }
+}
+
+/// Consumes a single partition, resuming from the last checkpointed offset (if any) and
+/// falling back to replaying from the beginning only on first run.
+async fn consume_partition(state: Arc<AppState>, partition: u32) {
     let consumer = state
         .fluvio
-        .partition_consumer(TOPIC_NAME, 0)
+        .partition_consumer(TOPIC_NAME, partition)
         .await
         .expect("Consumer error");
 
-    let mut stream = consumer.stream(Offset::beginning()).await.unwrap();
-    println!("Listening to Fluvio topic...");
+    let start = match state.offset_store.last_committed(partition).await {
+        Some(last) => Offset::absolute(last + 1).unwrap_or_else(|_| Offset::beginning()),
+        None => Offset::beginning(),
+    };
+
+    let mut stream = consumer.stream(start).await.unwrap();
+    println!("Listening to Fluvio topic {} partition {}...", TOPIC_NAME, partition);
 
     while let Some(Ok(record)) = stream.next().await {
         let value = record.value_string().unwrap();
         if let Ok(event) = serde_json::from_str::<EditEvent>(&value) {
             println!("Forwarding: {}", value);
             let _ = forward_to_webhook(&event).await;
-            let _ = state.tx.send(event);
+            let sender = state.room_sender(&event.doc_id).await;
+            let _ = sender.send(event);
+        }
+        state.offset_store.commit(partition, record.offset()).await;
+    }
+}
+
+/// Serves a `FetchHistory` request. Edits are produced keyed by `doc_id`, so a doc's
+/// history is hash-distributed across every partition of `TOPIC_NAME` (the same reason
+/// `consume_and_forward` fans out across `PARTITION_COUNT`) — replay every partition
+/// concurrently and merge the results, since each has an independent offset space and
+/// waiting on them one at a time would multiply the worst-case latency by `PARTITION_COUNT`.
+async fn fetch_history(
+    state: Arc<AppState>,
+    doc_id: String,
+    from_offsets: HashMap<u32, i64>,
+    limit: usize,
+) -> anyhow::Result<Vec<HistoryRecord>> {
+    let fetches = (0..PARTITION_COUNT).map(|partition| {
+        let state = state.clone();
+        let doc_id = doc_id.clone();
+        let from_offset = from_offsets.get(&partition).copied();
+        async move { fetch_partition_history(&state, partition, &doc_id, from_offset, limit).await }
+    });
+
+    let mut events = Vec::new();
+    for result in join_all(fetches).await {
+        events.extend(result?);
+    }
+    events.truncate(limit);
+    Ok(events)
+}
+
+/// Replays a single partition and collects up to `limit` records belonging to `doc_id`,
+/// starting from `from_offset` (or the beginning of the partition if `None`). This is
+/// called from a background task spawned per `FetchHistory` request, so it is safe for
+/// it to block that task: once the partition falls idle (no record within
+/// `FETCH_HISTORY_IDLE_TIMEOUT`) we stop and return what we have rather than tailing
+/// forever for history that doesn't exist yet.
+async fn fetch_partition_history(
+    state: &AppState,
+    partition: u32,
+    doc_id: &str,
+    from_offset: Option<i64>,
+    limit: usize,
+) -> anyhow::Result<Vec<HistoryRecord>> {
+    let consumer = state.fluvio.partition_consumer(TOPIC_NAME, partition).await?;
+    let start = match from_offset {
+        Some(offset) => Offset::absolute(offset)?,
+        None => Offset::beginning(),
+    };
+    let mut stream = consumer.stream(start).await?;
+
+    let mut events = Vec::with_capacity(limit);
+    while events.len() < limit {
+        let record = match timeout(FETCH_HISTORY_IDLE_TIMEOUT, stream.next()).await {
+            Ok(Some(Ok(record))) => record,
+            Ok(Some(Err(_))) | Ok(None) | Err(_) => break,
+        };
+        let offset = record.offset();
+        let value = record.value_string()?;
+        if let Ok(event) = serde_json::from_str::<EditEvent>(&value) {
+            if event.doc_id == doc_id {
+                events.push(HistoryRecord { partition, offset, event });
+            }
         }
     }
+    Ok(events)
 }
 
 async fn forward_to_webhook(event: &EditEvent) -> anyhow::Result<()> {
@@ -187,3 +586,41 @@ async fn forward_to_webhook(event: &EditEvent) -> anyhow::Result<()> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_filter_allows_everything() {
+        let filter = SubscriptionFilter::default();
+        assert!(filter.allows_kind("edit"));
+        assert!(filter.allows_op("insert"));
+        assert!(filter.allows_user("alice"));
+    }
+
+    #[test]
+    fn kinds_filter_restricts_to_the_listed_kinds() {
+        let filter = SubscriptionFilter { kinds: Some(vec!["edit".to_string()]), ops: None, user_ids: None };
+        assert!(filter.allows_kind("edit"));
+        assert!(!filter.allows_kind("typing"));
+    }
+
+    #[test]
+    fn ops_filter_restricts_to_the_listed_ops() {
+        let filter = SubscriptionFilter { kinds: None, ops: Some(vec!["insert".to_string()]), user_ids: None };
+        assert!(filter.allows_op("insert"));
+        assert!(!filter.allows_op("delete"));
+    }
+
+    #[test]
+    fn user_ids_filter_restricts_to_the_listed_users() {
+        let filter = SubscriptionFilter {
+            kinds: None,
+            ops: None,
+            user_ids: Some(vec!["alice".to_string()]),
+        };
+        assert!(filter.allows_user("alice"));
+        assert!(!filter.allows_user("bob"));
+    }
+}
@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tokio::sync::Mutex;
+
+/// Default location of the on-disk checkpoint file tracking the last forwarded offset
+/// per partition, relative to the working directory the server is started from.
+pub const DEFAULT_OFFSET_STORE_PATH: &str = "consumer_offsets.json";
+
+/// Tracks the last consumed offset per partition so a server restart can resume
+/// consumption instead of replaying the whole topic (and re-firing webhooks and
+/// broadcasts for records every client has already seen). A record's offset is
+/// committed once it has been handled, whether or not its webhook call succeeded.
+pub struct OffsetStore {
+    path: PathBuf,
+    offsets: Mutex<HashMap<u32, i64>>,
+}
+
+impl OffsetStore {
+    /// Loads the checkpoint file at `path` if one exists, or starts with no checkpoints.
+    pub async fn load(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let offsets = match tokio::fs::read(&path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_default(),
+            Err(_) => HashMap::new(),
+        };
+        Self { path, offsets: Mutex::new(offsets) }
+    }
+
+    /// Returns the checkpointed offset for `partition`, or `None` on first run.
+    pub async fn last_committed(&self, partition: u32) -> Option<i64> {
+        self.offsets.lock().await.get(&partition).copied()
+    }
+
+    /// Records `offset` as the last forwarded offset for `partition` and flushes the
+    /// whole checkpoint map to disk. The write happens while still holding the lock so
+    /// concurrent commits from different partition tasks can't land on disk out of order.
+    pub async fn commit(&self, partition: u32, offset: i64) {
+        let mut offsets = self.offsets.lock().await;
+        offsets.insert(partition, offset);
+        if let Ok(json) = serde_json::to_vec(&*offsets) {
+            let _ = tokio::fs::write(&self.path, json).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("fluvio-collab-backend-test-{}-{}.json", name, uuid::Uuid::new_v4()))
+    }
+
+    #[tokio::test]
+    async fn load_starts_empty_when_no_checkpoint_file_exists() {
+        let store = OffsetStore::load(scratch_path("load-empty")).await;
+        assert_eq!(store.last_committed(0).await, None);
+    }
+
+    #[tokio::test]
+    async fn commit_is_visible_immediately() {
+        let store = OffsetStore::load(scratch_path("commit-visible")).await;
+        store.commit(2, 42).await;
+        assert_eq!(store.last_committed(2).await, Some(42));
+        assert_eq!(store.last_committed(0).await, None);
+    }
+
+    #[tokio::test]
+    async fn commit_persists_across_a_reload() {
+        let path = scratch_path("commit-persists");
+        let store = OffsetStore::load(&path).await;
+        store.commit(1, 7).await;
+        store.commit(3, 99).await;
+
+        let reloaded = OffsetStore::load(&path).await;
+        assert_eq!(reloaded.last_committed(1).await, Some(7));
+        assert_eq!(reloaded.last_committed(3).await, Some(99));
+
+        let _ = tokio::fs::remove_file(&path).await;
+    }
+}